@@ -4,7 +4,6 @@ use leafwing_input_manager::action_state::ActionState;
 
 use serde::Deserialize;
 use serde::Serialize;
-use tungstenite::WebSocket;
 
 use std::collections::HashMap;
 use std::sync::mpsc::Receiver;
@@ -15,13 +14,14 @@ use crate::player;
 use crate::{
     berries::{Berry, BerryBundle},
     gates::{GateBundle, GATE_HEIGHT, GATE_NEUTRAL_IDX},
-    platforms::{PlatformBundle, PLATFORM_HEIGHT},
+    level::LevelDef,
+    mount::DismountEvent,
+    platforms::PlatformBundle,
     player::{Action, Player, PlayerController, Queen, SpawnPlayerEvent, Team},
     ship::RidingOnShip,
-    GameState, WINDOW_BOTTOM_Y, WINDOW_HEIGHT, WINDOW_RIGHT_X, WINDOW_WIDTH,
+    GameState,
 };
 
-const TEMP_PLATFORM_COLOR: Color = Color::BLACK;
 pub struct JoinPlugin;
 
 #[derive(Resource, Default)]
@@ -33,20 +33,41 @@ pub struct JoinedWebSockets(pub HashSet<i32>);
 #[derive(Resource, Default)]
 pub struct WebSocketControllers(pub HashMap<i32, ControllerState>);
 
+/// Handle to the join-screen layout, authored as a hot-reloadable level just
+/// like the play maps so its temp platforms and gates live in data.
+#[derive(Resource)]
+pub struct JoinLevel(pub Handle<LevelDef>);
+
+impl FromWorld for JoinLevel {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        JoinLevel(asset_server.load("levels/join.level.json"))
+    }
+}
+
 impl Plugin for JoinPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<JoinedGamepads>()
+            .init_resource::<JoinLevel>()
             .insert_resource(JoinedWebSockets::default())
             .insert_resource(WebSocketControllers::default())
+            .insert_resource(SessionReservations::default())
             .add_systems(
                 Update,
                 (
-                    (check_for_start_game, disconnect).run_if(in_state(GameState::Join)),
+                    setup_join.run_if(in_state(GameState::Join)),
+                    // Only look for "all gates claimed" once the gates have
+                    // actually been spawned from the join level; otherwise the
+                    // empty iterator makes `all` vacuously true and skips Join.
+                    check_for_start_game
+                        .run_if(in_state(GameState::Join))
+                        .run_if(any_with_component::<JoinGate>),
+                    disconnect.run_if(in_state(GameState::Join)),
                     join,
                     join_from_websocket,
+                    expire_reservations,
                 ),
             )
-            .add_systems(OnEnter(GameState::Join), setup_join)
             .add_systems(OnExit(GameState::Join), delete_temp_platforms);
     }
 }
@@ -70,48 +91,39 @@ fn setup_join(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut atlases: ResMut<Assets<TextureAtlasLayout>>,
+    join_level: Res<JoinLevel>,
+    levels: Res<Assets<LevelDef>>,
+    existing_gates: Query<Entity, With<JoinGate>>,
 ) {
-    for sign in [-1.0, 1.0] {
-        commands.spawn((
-            PlatformBundle::new(
-                sign * (WINDOW_RIGHT_X - WINDOW_WIDTH / 40.0 - WINDOW_WIDTH / 10.0
-                    + WINDOW_WIDTH / 60.0),
-                WINDOW_BOTTOM_Y + 7.0 * WINDOW_HEIGHT / 9.0,
-                Vec3::new(
-                    (WINDOW_RIGHT_X - WINDOW_WIDTH / 20.0)
-                        - (WINDOW_RIGHT_X - WINDOW_WIDTH / 5.0 + WINDOW_WIDTH / 30.0),
-                    PLATFORM_HEIGHT / 4.0,
-                    1.0,
-                ),
-                true,
-                Some(TEMP_PLATFORM_COLOR),
-                &asset_server,
-            ),
-            TempPlatform,
-        ));
+    // Spawn once, after the join level has finished loading asynchronously.
+    // While the handle is unresolved this is a no-op and we try again next
+    // frame; once the gates exist we stop re-spawning.
+    if !existing_gates.is_empty() {
+        return;
+    }
+    let Some(level) = levels.get(&join_level.0) else {
+        return;
+    };
+
+    for platform in &level.platforms {
         commands.spawn((
             PlatformBundle::new(
-                sign * (((WINDOW_WIDTH / 10.0)
-                    + (WINDOW_RIGHT_X - WINDOW_WIDTH / 5.0 - WINDOW_WIDTH / 30.0))
-                    / 2.0),
-                WINDOW_BOTTOM_Y + 7.0 * WINDOW_HEIGHT / 9.0,
-                Vec3::new(
-                    (WINDOW_RIGHT_X - WINDOW_WIDTH / 5.0 - WINDOW_WIDTH / 30.0)
-                        - WINDOW_WIDTH / 10.0,
-                    PLATFORM_HEIGHT / 4.0,
-                    1.0,
-                ),
-                true,
-                Some(TEMP_PLATFORM_COLOR),
+                platform.pos.x,
+                platform.pos.y,
+                Vec3::new(platform.size.x, platform.size.y, 1.0),
+                platform.one_way,
+                platform.color.map(Color::from),
                 &asset_server,
             ),
             TempPlatform,
         ));
+    }
 
+    for gate in &level.gates {
         commands.spawn((
             GateBundle::new(
-                (WINDOW_RIGHT_X - WINDOW_WIDTH / 3.2) * sign,
-                WINDOW_BOTTOM_Y + 8.0 * WINDOW_HEIGHT / 9.0 + GATE_HEIGHT / 2.0,
+                gate.pos.x,
+                gate.pos.y + GATE_HEIGHT / 2.0,
                 &asset_server,
                 &mut atlases,
             ),
@@ -174,7 +186,16 @@ fn join(
 }
 
 #[derive(Resource)]
-pub struct BevyReceiver(pub Arc<Mutex<Receiver<ControllerState>>>);
+pub struct BevyReceiver(pub Arc<Mutex<Receiver<NetEvent>>>);
+
+/// Lifecycle message pushed from the network thread to the Bevy side. Input
+/// frames and clean disconnects flow over the same channel so slot reservation
+/// can react to a dropped socket without the connection thread panicking.
+#[derive(Debug, Clone, Copy)]
+pub enum NetEvent {
+    Input(ControllerState),
+    Disconnected { token: u64 },
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct ControllerState {
@@ -183,97 +204,236 @@ pub struct ControllerState {
     is_leaving: bool,
     x_movement: f32,
     jump: bool,
+    /// Persistent session token assigned on the join handshake; the client
+    /// echoes it back so a reconnecting controller can reclaim its slot.
+    #[serde(default)]
+    token: u64,
 }
 
+impl ControllerState {
+    /// Stamp this frame with the session token assigned on the join handshake.
+    pub fn with_token(mut self, token: u64) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// The protocol-level player id, stable across peers and reconnects.
+    pub fn player_id(&self) -> i32 {
+        self.player
+    }
+}
+
+/// How long a disconnected player's queen slot, team and gate stay reserved
+/// before they are freed, giving a flaky connection time to come back.
+const RECONNECT_GRACE: f32 = 10.0;
+
+/// A slot held open for a dropped connection until [`RECONNECT_GRACE`] elapses.
+pub struct Reservation {
+    pub team: Team,
+    pub is_queen: bool,
+    pub player_id: i32,
+    pub timer: Timer,
+}
+
+#[derive(Resource, Default)]
+pub struct SessionReservations(pub HashMap<u64, Reservation>);
+
 fn join_from_websocket(
     mut commands: Commands,
     mut joined_websockets: ResMut<JoinedWebSockets>,
     mut web_socket_controllers: ResMut<WebSocketControllers>,
     receiver: Res<BevyReceiver>,
-    action_query: Query<(
-        Entity,
-        &ActionState<Action>,
-        &Player,
-        Has<Berry>,
-        &Transform,
-        Option<&RidingOnShip>,
-        &Team,
-        Has<Queen>,
-    )>,
+    action_query: PlayerQuery,
     queens: Query<&Team, With<Queen>>,
     mut ev_spawn_players: EventWriter<SpawnPlayerEvent>,
     asset_server: Res<AssetServer>,
     mut join_gates: Query<(Entity, &Team, &mut TextureAtlas), With<JoinGate>>,
+    mut ev_dismount: EventWriter<DismountEvent>,
+    mut reservations: ResMut<SessionReservations>,
 ) {
-    match receiver.0.lock() {
-        Ok(receiver) => {
-            while let Ok(controller_update) = receiver.try_recv() {
-                let player_id = controller_update.player.clone();
-                if joined_websockets.0.contains(&player_id) {
-                    if controller_update.is_leaving {
-                        for (
+    let Ok(receiver) = receiver.0.lock() else {
+        return;
+    };
+    while let Ok(event) = receiver.try_recv() {
+        let controller_update = match event {
+            NetEvent::Input(controller_update) => controller_update,
+            NetEvent::Disconnected { token } => {
+                // A dropped socket keeps its slot reserved rather than freeing
+                // it immediately, so a flaky client can reconnect in time.
+                reserve_slot(token, &mut reservations, &action_query);
+                continue;
+            }
+        };
+
+        let player_id = controller_update.player;
+        // A reconnecting controller presents the token it was issued on join; if
+        // its slot is still reserved, cancel the reservation and keep the slot.
+        if reservations.0.remove(&controller_update.token).is_some() {
+            println!("player reconnected");
+        }
+        if joined_websockets.0.contains(&player_id) {
+            if controller_update.is_leaving {
+                for (
+                    player_entity,
+                    _,
+                    player,
+                    killed_has_berry,
+                    killed_player_transform,
+                    maybe_riding_on_ship,
+                    team,
+                    is_queen,
+                ) in action_query.iter()
+                {
+                    if let PlayerController::WebSocket(state) = player.player_controller {
+                        if state.player != player_id {
+                            continue;
+                        }
+                        remove_player(
+                            &mut commands,
                             player_entity,
-                            _,
-                            player,
                             killed_has_berry,
                             killed_player_transform,
+                            &asset_server,
                             maybe_riding_on_ship,
-                            team,
-                            is_queen,
-                        ) in action_query.iter()
-                        {
-                            match player.player_controller {
-                                WebSocket => {
-                                    remove_player(
-                                        &mut commands,
-                                        player_entity,
-                                        killed_has_berry,
-                                        killed_player_transform,
-                                        &asset_server,
-                                        maybe_riding_on_ship,
-                                    );
-                                    if is_queen {
-                                        for (join_gate, join_gate_team, mut gate_sprite) in
-                                            join_gates.iter_mut()
-                                        {
-                                            if join_gate_team == team {
-                                                commands.entity(join_gate).remove::<Team>();
-                                                gate_sprite.index = GATE_NEUTRAL_IDX;
-                                            }
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
+                            *team,
+                            // Every entity in this query carries `Player`.
+                            true,
+                            &mut ev_dismount,
+                        );
+                        if is_queen {
+                            free_gate(&mut commands, team, &mut join_gates);
                         }
-                        joined_websockets.0.remove(&player_id);
-                    } else {
-                        web_socket_controllers
-                            .0
-                            .insert(player_id, controller_update);
                     }
-                } else {
-                    println!("player joining");
-                    let team = if controller_update.is_purple {
-                        Team::Purple
-                    } else {
-                        Team::Yellow
-                    };
-                    let is_queen = !queens.iter().any(|&queen_team| queen_team == team);
-                    ev_spawn_players.send(SpawnPlayerEvent {
-                        team,
+                }
+                reservations.0.remove(&controller_update.token);
+                joined_websockets.0.remove(&player_id);
+            } else {
+                web_socket_controllers.0.insert(player_id, controller_update);
+            }
+        } else {
+            println!("player joining");
+            let team = if controller_update.is_purple {
+                Team::Purple
+            } else {
+                Team::Yellow
+            };
+            let is_queen = !queens.iter().any(|&queen_team| queen_team == team);
+            ev_spawn_players.send(SpawnPlayerEvent {
+                team,
+                is_queen,
+                player_controller: PlayerController::WebSocket(controller_update),
+                delay: 0.0,
+                start_invincible: false,
+            });
+            // Insert the created player and its gamepad to the hashmap of joined players
+            // Since uniqueness was already checked above, we can insert here unchecked
+            joined_websockets.0.insert(player_id);
+        }
+    }
+}
+
+type PlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static ActionState<Action>,
+        &'static Player,
+        Has<Berry>,
+        &'static Transform,
+        Option<&'static RidingOnShip>,
+        &'static Team,
+        Has<Queen>,
+    ),
+>;
+
+/// Reserve the slot belonging to `token` so it survives a dropped connection.
+fn reserve_slot(
+    token: u64,
+    reservations: &mut SessionReservations,
+    action_query: &PlayerQuery,
+) {
+    for (_, _, player, _, _, _, team, is_queen) in action_query.iter() {
+        if let PlayerController::WebSocket(state) = player.player_controller {
+            if state.token == token {
+                reservations.0.insert(
+                    token,
+                    Reservation {
+                        team: *team,
                         is_queen,
-                        player_controller: PlayerController::WebSocket(controller_update),
-                        delay: 0.0,
-                        start_invincible: false,
-                    });
-                    // Insert the created player and its gamepad to the hashmap of joined players
-                    // Since uniqueness was already checked above, we can insert here unchecked
-                    joined_websockets.0.insert(player_id);
+                        player_id: state.player,
+                        timer: Timer::from_seconds(RECONNECT_GRACE, TimerMode::Once),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Neutralize the join gate owned by `team` when its queen's slot is freed.
+fn free_gate(
+    commands: &mut Commands,
+    team: &Team,
+    join_gates: &mut Query<(Entity, &Team, &mut TextureAtlas), With<JoinGate>>,
+) {
+    for (join_gate, join_gate_team, mut gate_sprite) in join_gates.iter_mut() {
+        if join_gate_team == team {
+            commands.entity(join_gate).remove::<Team>();
+            gate_sprite.index = GATE_NEUTRAL_IDX;
+        }
+    }
+}
+
+/// Tick down pending reservations and, once the grace period lapses without a
+/// reconnect, remove the player and release their gate for good.
+#[allow(clippy::too_many_arguments)]
+fn expire_reservations(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut reservations: ResMut<SessionReservations>,
+    mut joined_websockets: ResMut<JoinedWebSockets>,
+    action_query: PlayerQuery,
+    asset_server: Res<AssetServer>,
+    mut join_gates: Query<(Entity, &Team, &mut TextureAtlas), With<JoinGate>>,
+    mut ev_dismount: EventWriter<DismountEvent>,
+) {
+    let mut expired = Vec::new();
+    for (token, reservation) in reservations.0.iter_mut() {
+        reservation.timer.tick(time.delta());
+        if reservation.timer.finished() {
+            expired.push(*token);
+        }
+    }
+
+    for token in expired {
+        let Some(reservation) = reservations.0.remove(&token) else {
+            continue;
+        };
+        for (player_entity, _, player, has_berry, transform, maybe_riding, team, is_queen) in
+            action_query.iter()
+        {
+            if let PlayerController::WebSocket(state) = player.player_controller {
+                if state.token != token {
+                    continue;
+                }
+                remove_player(
+                    &mut commands,
+                    player_entity,
+                    has_berry,
+                    transform,
+                    &asset_server,
+                    maybe_riding,
+                    *team,
+                    // Every entity in this query carries `Player`.
+                    true,
+                    &mut ev_dismount,
+                );
+                if reservation.is_queen {
+                    free_gate(&mut commands, team, &mut join_gates);
                 }
             }
         }
-        Err(_) => (),
+        joined_websockets.0.remove(&reservation.player_id);
     }
 }
 
@@ -292,6 +452,7 @@ fn disconnect(
     mut joined_gamepads: ResMut<JoinedGamepads>,
     asset_server: Res<AssetServer>,
     mut join_gates: Query<(Entity, &Team, &mut TextureAtlas), With<JoinGate>>,
+    mut ev_dismount: EventWriter<DismountEvent>,
 ) {
     for (
         player_entity,
@@ -315,6 +476,10 @@ fn disconnect(
                 killed_player_transform,
                 &asset_server,
                 maybe_riding_on_ship,
+                *team,
+                // Every entity in this query carries `Player`.
+                true,
+                &mut ev_dismount,
             );
             if is_queen {
                 for (join_gate, join_gate_team, mut gate_sprite) in join_gates.iter_mut() {
@@ -336,6 +501,9 @@ pub fn remove_player(
     transform: &Transform,
     asset_server: &Res<AssetServer>,
     maybe_riding_on_ship: Option<&RidingOnShip>,
+    team: Team,
+    is_player: bool,
+    ev_dismount: &mut EventWriter<DismountEvent>,
 ) {
     // Despawn the disconnected player and remove them from the joined player list
     commands.entity(player_entity).despawn_recursive();
@@ -348,7 +516,14 @@ pub fn remove_player(
             asset_server,
         ));
     }
+    // Let the mount subsystem handle vehicle bookkeeping and team neutralization
+    // instead of poking `RidingOnShip`/`Team` here.
     if let Some(riding_on_ship) = maybe_riding_on_ship {
-        commands.entity(riding_on_ship.ship).remove::<Team>();
+        ev_dismount.send(DismountEvent {
+            rider: player_entity,
+            vehicle: riding_on_ship.ship,
+            team,
+            is_player,
+        });
     }
 }
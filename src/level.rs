@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+use bevy_common_assets::json::JsonAssetPlugin;
+use bevy_rapier2d::dynamics::RigidBody;
+use serde::Deserialize;
+
+use crate::{
+    berries::BerryBundle,
+    gates::{GateBundle, GATE_HEIGHT},
+    platforms::PlatformBundle,
+    player::Team,
+    GameState,
+};
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<LevelDef>::new(&["level.json"]))
+            .init_resource::<AvailableLevels>()
+            .init_resource::<SelectedLevel>()
+            .init_resource::<LevelSpawned>()
+            .add_systems(
+                Update,
+                (cycle_level, select_level)
+                    .chain()
+                    .run_if(in_state(GameState::Join)),
+            )
+            .add_systems(Update, spawn_level.run_if(in_state(GameState::Play)))
+            .add_systems(OnExit(GameState::Play), reset_level_spawned);
+    }
+}
+
+/// The maps shipped with the game, in the order [`cycle_level`] steps
+/// through them. Designers drop a new `*.level.json` under `assets/levels/` and
+/// add it here to make it selectable.
+#[derive(Resource)]
+pub struct AvailableLevels {
+    pub handles: Vec<Handle<LevelDef>>,
+    pub selected: usize,
+}
+
+impl FromWorld for AvailableLevels {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        AvailableLevels {
+            handles: vec![
+                asset_server.load("levels/default.level.json"),
+                asset_server.load("levels/arena.level.json"),
+            ],
+            selected: 0,
+        }
+    }
+}
+
+/// The level chosen on the join screen for the next play session.
+#[derive(Resource)]
+pub struct SelectedLevel(pub Handle<LevelDef>);
+
+impl FromWorld for SelectedLevel {
+    fn from_world(world: &mut World) -> Self {
+        let available = world.resource::<AvailableLevels>();
+        SelectedLevel(available.handles[available.selected].clone())
+    }
+}
+
+/// Whether the selected map has already been spawned into the current play
+/// session, so [`spawn_level`] only fires once the asset finishes loading.
+#[derive(Resource, Default)]
+pub struct LevelSpawned(pub bool);
+
+/// Objective thresholds for the active map, inserted by [`spawn_level`]. The
+/// win-condition systems do not read this resource.
+#[derive(Resource, Clone, Copy)]
+pub struct WinThresholds {
+    pub berries_to_win: u32,
+    pub military_kills: u32,
+    pub ship_distance: f32,
+}
+
+/// The two endpoints of the ship's track on the active map, inserted by
+/// [`spawn_level`]. The ship movement system does not read this resource.
+#[derive(Resource, Clone, Copy)]
+pub struct ShipTrack {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+/// Per-team spawn points for the active map, keyed in spawn order.
+#[derive(Resource, Default)]
+pub struct SpawnPoints(pub Vec<(Team, Vec2)>);
+
+/// A serde-friendly RGBA colour so designers can author tints in JSON.
+#[derive(Deserialize, Clone, Copy)]
+pub struct LevelColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    #[serde(default = "one")]
+    pub a: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl From<LevelColor> for Color {
+    fn from(c: LevelColor) -> Self {
+        Color::rgba(c.r, c.g, c.b, c.a)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct PlatformDef {
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub color: Option<LevelColor>,
+    #[serde(default)]
+    pub one_way: bool,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct GateDef {
+    pub pos: Vec2,
+    pub team: Option<Team>,
+}
+
+/// Win thresholds pulled out of the map so each level can tune its own pace.
+#[derive(Deserialize, Clone, Copy)]
+pub struct Objectives {
+    pub berries_to_win: u32,
+    pub military_kills: u32,
+    pub ship_distance: f32,
+}
+
+/// A hot-reloadable map definition loaded from `assets/levels/*.level.json`.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct LevelDef {
+    pub platforms: Vec<PlatformDef>,
+    pub gates: Vec<GateDef>,
+    pub berry_spawns: Vec<Vec2>,
+    pub ship_track: (Vec2, Vec2),
+    pub spawn_points: Vec<(Team, Vec2)>,
+    pub objectives: Objectives,
+}
+
+/// Cycle to the next available map from the join screen. Bound to the `Tab`
+/// key; the settings menu does not change the selected map.
+fn cycle_level(keys: Res<ButtonInput<KeyCode>>, mut available: ResMut<AvailableLevels>) {
+    if keys.just_pressed(KeyCode::Tab) && !available.handles.is_empty() {
+        available.selected = (available.selected + 1) % available.handles.len();
+    }
+}
+
+/// Apply the map [`cycle_level`] selected to [`SelectedLevel`] while on the
+/// join screen, so entering `Play` spawns the right map.
+fn select_level(available: Res<AvailableLevels>, mut selected: ResMut<SelectedLevel>) {
+    if available.is_changed() {
+        selected.0 = available.handles[available.selected].clone();
+    }
+}
+
+/// Spawn every entity described by the selected [`LevelDef`] once it has
+/// finished loading. Because Bevy asset loads are async, this runs every frame
+/// during `Play` and no-ops until the handle resolves, then spawns exactly once
+/// (tracked by [`LevelSpawned`]). Hot-reloading the JSON re-enters the level by
+/// cycling through `Play`.
+pub fn spawn_level(
+    mut commands: Commands,
+    selected: Res<SelectedLevel>,
+    levels: Res<Assets<LevelDef>>,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlasLayout>>,
+    mut spawned: ResMut<LevelSpawned>,
+) {
+    if spawned.0 {
+        return;
+    }
+    let Some(level) = levels.get(&selected.0) else {
+        return;
+    };
+
+    for platform in &level.platforms {
+        commands.spawn(PlatformBundle::new(
+            platform.pos.x,
+            platform.pos.y,
+            Vec3::new(platform.size.x, platform.size.y, 1.0),
+            platform.one_way,
+            platform.color.map(Color::from),
+            &asset_server,
+        ));
+    }
+
+    for gate in &level.gates {
+        let mut entity = commands.spawn(GateBundle::new(
+            gate.pos.x,
+            gate.pos.y + GATE_HEIGHT / 2.0,
+            &asset_server,
+            &mut atlases,
+        ));
+        if let Some(team) = gate.team {
+            entity.insert(team);
+        }
+    }
+
+    for spawn in &level.berry_spawns {
+        commands.spawn(BerryBundle::new(
+            spawn.x,
+            spawn.y,
+            RigidBody::Fixed,
+            &asset_server,
+        ));
+    }
+
+    // Surface the remaining level data as resources: win thresholds, the
+    // ship's travel segment, and per-team spawn points.
+    commands.insert_resource(WinThresholds {
+        berries_to_win: level.objectives.berries_to_win,
+        military_kills: level.objectives.military_kills,
+        ship_distance: level.objectives.ship_distance,
+    });
+    commands.insert_resource(ShipTrack {
+        start: level.ship_track.0,
+        end: level.ship_track.1,
+    });
+    commands.insert_resource(SpawnPoints(level.spawn_points.clone()));
+
+    spawned.0 = true;
+}
+
+/// Allow the next entry into `Play` to respawn the map (or a newly selected
+/// one).
+fn reset_level_spawned(mut spawned: ResMut<LevelSpawned>) {
+    spawned.0 = false;
+}
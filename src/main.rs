@@ -4,38 +4,47 @@ mod animation;
 mod berries;
 mod gates;
 mod join;
+mod level;
+mod mount;
 #[cfg(feature = "bevy_midi")]
 mod midi;
 mod platforms;
 mod player;
+mod rollback;
 mod settings;
 mod ship;
+mod spectator;
 
 use animation::AnimationPlugin;
 use berries::BerriesPlugin;
 use bevy::{prelude::*, render::camera::ScalingMode, window::WindowResolution};
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
-use join::{BevyReceiver, ControllerState};
+use join::{BevyReceiver, ControllerState, NetEvent};
 // use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier2d::prelude::*;
 use gates::GatePlugin;
 use iyes_perf_ui::{diagnostics::PerfUiEntryFPS, PerfUiPlugin, PerfUiRoot};
 use join::JoinPlugin;
+use level::LevelPlugin;
+use mount::MountPlugin;
 #[cfg(feature = "bevy_midi")]
 use midi::MidiPlugin;
 use platforms::PlatformsPlugin;
 use player::{PlayerPlugin, Team};
+use rollback::RollbackPlugin;
 use settings::SettingsPlugin;
 use ship::ShipPlugin;
+use spectator::{GameSnapshot, SnapshotSender, Spectators, SpectatorPlugin};
 
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread::{self, JoinHandle};
 
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
 use std::thread::spawn;
-use tungstenite::accept;
+use tungstenite::{accept, Message, WebSocket};
 
 const WINDOW_WIDTH: f32 = 1920.0;
 const WINDOW_HEIGHT: f32 = 1016.0;
@@ -48,11 +57,13 @@ pub const WINDOW_RIGHT_X: f32 = WINDOW_WIDTH / 2.0;
 const COLOR_BACKGROUND: Color = Color::rgb(0.298, 0.737, 0.937);
 
 fn main() {
-    let (controller_server, receiver) = setup_controller_websocket();
+    let (controller_server, receiver, spectators, snapshot_sender) = setup_controller_websocket();
     let bevy_receiver = BevyReceiver(Arc::new(Mutex::new(receiver)));
     App::new()
         .insert_resource(ClearColor(COLOR_BACKGROUND))
         .insert_resource(bevy_receiver)
+        .insert_resource(spectators)
+        .insert_resource(SnapshotSender(snapshot_sender))
         .init_state::<GameState>()
         .add_plugins(
             DefaultPlugins
@@ -68,7 +79,11 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
         )
         .add_plugins((
-            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0),
+            // Physics runs inside the GGRS rollback schedule (not the main
+            // Update schedule) so it is re-simulated on every rewind and stays
+            // deterministic.
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0)
+                .in_schedule(bevy_ggrs::GgrsSchedule),
             // RapierDebugRenderPlugin::default(),
             PlatformsPlugin,
             PlayerPlugin,
@@ -77,7 +92,11 @@ fn main() {
             ShipPlugin,
             GatePlugin,
             JoinPlugin,
+            LevelPlugin,
+            MountPlugin,
+            RollbackPlugin,
             SettingsPlugin,
+            SpectatorPlugin,
             #[cfg(feature = "bevy_midi")]
             MidiPlugin,
         ))
@@ -96,28 +115,125 @@ fn main() {
         .expect("Web socket thread panicked");
 }
 
-fn setup_controller_websocket() -> (JoinHandle<()>, Receiver<ControllerState>) {
-    let (transmitter, receiver): (Sender<ControllerState>, Receiver<ControllerState>) =
+fn setup_controller_websocket() -> (
+    JoinHandle<()>,
+    Receiver<NetEvent>,
+    Spectators,
+    Sender<GameSnapshot>,
+) {
+    let (transmitter, receiver): (Sender<NetEvent>, Receiver<NetEvent>) = mpsc::channel();
+
+    // Monotonic source of per-connection session tokens.
+    let next_token = Arc::new(AtomicU64::new(1));
+
+    // Spectators connect read-only; the Bevy side hands us snapshots on this
+    // channel and we fan them out to every spectator socket.
+    let spectators = Spectators::default();
+    let (snapshot_sender, snapshot_receiver): (Sender<GameSnapshot>, Receiver<GameSnapshot>) =
         mpsc::channel();
 
+    {
+        let spectators = spectators.0.clone();
+        spawn(move || {
+            while let Ok(snapshot) = snapshot_receiver.recv() {
+                let Ok(payload) = serde_json::to_string(&snapshot) else {
+                    continue;
+                };
+                let mut sockets = spectators.lock().unwrap();
+                // Drop any spectator whose send fails (disconnected/backed up).
+                sockets.retain_mut(|socket| socket.send(Message::text(payload.clone())).is_ok());
+            }
+        });
+    }
+
+    let thread_spectators = spectators.0.clone();
     let web_socket_thread = thread::spawn(move || {
         let server = TcpListener::bind("10.0.0.184:8000").unwrap();
         println!("Server is listing");
         for stream in server.incoming() {
             let connection_transmitter = transmitter.clone();
+            let spectators = thread_spectators.clone();
+            let next_token = next_token.clone();
             spawn(move || {
-                let mut websocket = accept(stream.unwrap()).unwrap();
+                let Ok(stream) = stream else {
+                    return;
+                };
+                let Ok(mut websocket) = accept(stream) else {
+                    return;
+                };
                 println!("Connection successful");
-                loop {
-                    let msg = websocket.read().unwrap();
-                    let rocket_message: ControllerState =
-                        serde_json::from_str(&msg.to_string()).unwrap();
-                    let _ = connection_transmitter.send(rocket_message);
+
+                // The first frame is a handshake declaring the connection's
+                // role. Anything but an explicit spectator request is treated
+                // as a controller so existing clients keep working.
+                match websocket.read() {
+                    Ok(msg) if is_spectator_handshake(&msg.to_string()) => {
+                        spectators.lock().unwrap().push(websocket);
+                    }
+                    Ok(msg) => {
+                        // Reuse the token the client presents (a reconnecting
+                        // controller echoes the one it was issued before the
+                        // drop). A fresh client sends token 0, so we mint one
+                        // and hand it back for it to remember.
+                        let presented = parse_token(&msg.to_string());
+                        let token = if presented != 0 {
+                            presented
+                        } else {
+                            let minted = next_token.fetch_add(1, Ordering::Relaxed);
+                            let _ = websocket
+                                .send(Message::text(format!("{{\"token\":{minted}}}")));
+                            minted
+                        };
+                        forward_controller(&msg.to_string(), token, &connection_transmitter);
+                        run_controller_loop(websocket, token, connection_transmitter);
+                    }
+                    Err(_) => {}
                 }
             });
         }
     });
-    return (web_socket_thread, receiver);
+    (web_socket_thread, receiver, spectators, snapshot_sender)
+}
+
+/// A handshake frame opts a connection into the read-only spectator stream.
+fn is_spectator_handshake(raw: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Handshake {
+        role: String,
+    }
+    serde_json::from_str::<Handshake>(raw)
+        .map(|h| h.role == "spectator")
+        .unwrap_or(false)
+}
+
+/// Read the session token a controller frame carries, defaulting to 0 (meaning
+/// "no token yet — please mint one") for first-time or malformed frames.
+fn parse_token(raw: &str) -> u64 {
+    #[derive(serde::Deserialize)]
+    struct TokenOnly {
+        #[serde(default)]
+        token: u64,
+    }
+    serde_json::from_str::<TokenOnly>(raw)
+        .map(|t| t.token)
+        .unwrap_or(0)
+}
+
+/// Parse one controller frame, stamp it with the connection's session token,
+/// and forward it; malformed frames are dropped rather than panicking.
+fn forward_controller(raw: &str, token: u64, transmitter: &Sender<NetEvent>) {
+    if let Ok(state) = serde_json::from_str::<ControllerState>(raw) {
+        let _ = transmitter.send(NetEvent::Input(state.with_token(token)));
+    }
+}
+
+/// Pump controller frames until the socket closes, then signal a clean
+/// disconnect so the Bevy side can reserve the slot for a reconnect.
+fn run_controller_loop(mut websocket: WebSocket<TcpStream>, token: u64, transmitter: Sender<NetEvent>) {
+    while let Ok(msg) = websocket.read() {
+        forward_controller(&msg.to_string(), token, &transmitter);
+    }
+    let _ = transmitter.send(NetEvent::Disconnected { token });
 }
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
@@ -145,7 +261,7 @@ fn setup(mut commands: Commands) {
     commands.spawn(camera);
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Resource, Debug, Clone, Copy)]
 pub enum WinCondition {
     Military,
     Economic,
@@ -173,6 +289,9 @@ fn set_win_text(
     }
     for win_event in ev_win.read() {
         next_state.set(GameState::GameOver);
+        // Publish the decided win condition so the spectator snapshot can
+        // report it; cleared when the next game starts.
+        commands.insert_resource(win_event.win_condition);
         let font = asset_server.load("fonts/FiraSans-Bold.ttf");
         let text_style = TextStyle {
             font: font.clone(),
@@ -221,6 +340,7 @@ fn start_next_game(
 
         if next_game_timer.timer.finished() {
             commands.entity(entity).despawn();
+            commands.remove_resource::<WinCondition>();
             next_state.set(GameState::Join);
         }
     }
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use crate::player::{Player, Team};
+use crate::ship::{RidingOnShip, Ship};
+
+/// How many riders the ship can carry at once.
+const SHIP_CAPACITY: usize = 1;
+
+pub struct MountPlugin;
+
+impl Plugin for MountPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MountEvent>()
+            .add_event::<DismountEvent>()
+            .add_systems(
+                Update,
+                (
+                    attach_rideable,
+                    detect_boarding,
+                    handle_mount,
+                    handle_dismount,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Give every ship a [`Rideable`] so the mount subsystem can track who is
+/// aboard and which team owns it.
+fn attach_rideable(mut commands: Commands, ships: Query<Entity, (With<Ship>, Without<Rideable>)>) {
+    for ship in ships.iter() {
+        commands.entity(ship).insert(Rideable::new(SHIP_CAPACITY));
+    }
+}
+
+/// Bridge the existing collision-driven boarding (which inserts
+/// [`RidingOnShip`]) into a [`MountEvent`] so capture/animation logic lives in
+/// one place.
+fn detect_boarding(
+    mut ev_mount: EventWriter<MountEvent>,
+    boarded: Query<(Entity, &RidingOnShip, &Team, Has<Player>), Added<RidingOnShip>>,
+) {
+    for (rider, riding, team, is_player) in boarded.iter() {
+        ev_mount.send(MountEvent {
+            rider,
+            vehicle: riding.ship,
+            team: *team,
+            is_player,
+        });
+    }
+}
+
+/// A vehicle that riders can board. Generalizes the old ad-hoc ship boarding so
+/// new rideable objects only need to spawn this component to participate.
+#[derive(Component)]
+pub struct Rideable {
+    pub capacity: usize,
+    pub riders: Vec<Entity>,
+    pub owning_team: Option<Team>,
+}
+
+impl Rideable {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            riders: Vec::new(),
+            owning_team: None,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.riders.len() >= self.capacity
+    }
+}
+
+/// Emitted when a rider wants to board `vehicle`. The mount subsystem is the
+/// single place that decides whether the board succeeds and captures the team.
+#[derive(Event)]
+pub struct MountEvent {
+    pub rider: Entity,
+    pub vehicle: Entity,
+    pub team: Team,
+    pub is_player: bool,
+}
+
+/// Emitted when a rider leaves `vehicle`, whether by dismounting or dying.
+#[derive(Event)]
+pub struct DismountEvent {
+    pub rider: Entity,
+    pub vehicle: Entity,
+    pub team: Team,
+    pub is_player: bool,
+}
+
+/// Board riders onto vehicles: record the rider and let each boarding player
+/// capture the vehicle for their team. The [`RidingOnShip`] tag is owned
+/// by the boarding code that raised the event, so we only do the bookkeeping
+/// here.
+fn handle_mount(
+    mut ev_mount: EventReader<MountEvent>,
+    mut commands: Commands,
+    mut rideables: Query<&mut Rideable>,
+) {
+    for event in ev_mount.read() {
+        let Ok(mut rideable) = rideables.get_mut(event.vehicle) else {
+            continue;
+        };
+        if rideable.is_full() || rideable.riders.contains(&event.rider) {
+            continue;
+        }
+        rideable.riders.push(event.rider);
+        if event.is_player {
+            rideable.owning_team = Some(event.team);
+            commands.entity(event.vehicle).insert(event.team);
+        }
+    }
+}
+
+/// Remove riders from vehicles and neutralize a vehicle when a rider of its
+/// capturing team leaves and nobody is left aboard.
+fn handle_dismount(
+    mut ev_dismount: EventReader<DismountEvent>,
+    mut commands: Commands,
+    mut rideables: Query<&mut Rideable>,
+) {
+    for event in ev_dismount.read() {
+        let Ok(mut rideable) = rideables.get_mut(event.vehicle) else {
+            continue;
+        };
+        rideable.riders.retain(|&rider| rider != event.rider);
+
+        // Neutralize the vehicle when an owning-team rider leaves it empty.
+        if rideable.owning_team == Some(event.team) && rideable.riders.is_empty() {
+            rideable.owning_team = None;
+            commands.entity(event.vehicle).remove::<Team>();
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+use bevy_ggrs::prelude::*;
+use bevy_ggrs::{GgrsApp, GgrsSchedule, LocalInputs, LocalPlayers, ReadInputs};
+use bevy_rapier2d::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+use ggrs::{PlayerType, SessionBuilder, UdpNonBlockingSocket};
+
+use std::net::SocketAddr;
+
+use crate::player::{Action, Player, PlayerController, Team, PLAYER_JUMP_IMPULSE, PLAYER_SPEED};
+use crate::GameState;
+
+/// The fixed rate, in frames per second, that the rollback schedule runs at.
+/// GGRS advances the simulation by exactly one step per frame and Rapier is
+/// pinned to the same value so the world stays a pure function of the
+/// rolled-back state.
+pub const FPS: usize = 60;
+const FIXED_DT: f32 = 1.0 / FPS as f32;
+
+const MAX_PREDICTION: usize = 12;
+const INPUT_DELAY: usize = 2;
+const UDP_PORT: u16 = 7810;
+
+/// Upper bound on concurrent players (Killer Queen is up to 5v5). The session
+/// and `PlayerInputs` are sized to this unless `KQ_NUM_PLAYERS` sets a smaller
+/// table, and [`stable_handle`] gives no handle to an identity at or above it.
+pub const MAX_PLAYERS: usize = 10;
+
+/// The packed input frame exchanged between peers every step. Kept as small
+/// and `Pod` as possible so GGRS can memcmp confirmed inputs cheaply; the
+/// horizontal axis is quantized to an `i8` and the remaining controls live in
+/// a single bitfield.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, Default)]
+pub struct BoxInput {
+    /// Horizontal movement in the range `[-127, 127]`, mapping back to
+    /// `[-1.0, 1.0]` when applied.
+    pub x_movement: i8,
+    /// Bit 0: jump held. Bit 1: on the purple team. Bit 2: leaving.
+    pub buttons: u8,
+}
+
+const BTN_JUMP: u8 = 1 << 0;
+const BTN_PURPLE: u8 = 1 << 1;
+const BTN_LEAVE: u8 = 1 << 2;
+
+impl BoxInput {
+    pub fn x_movement(&self) -> f32 {
+        self.x_movement as f32 / i8::MAX as f32
+    }
+
+    pub fn jump(&self) -> bool {
+        self.buttons & BTN_JUMP != 0
+    }
+
+    pub fn is_purple(&self) -> bool {
+        self.buttons & BTN_PURPLE != 0
+    }
+
+    pub fn is_leaving(&self) -> bool {
+        self.buttons & BTN_LEAVE != 0
+    }
+}
+
+/// GGRS session configuration for Killer Queen. The state is a unit type
+/// because all rolled-back data lives in ECS components registered below.
+#[derive(Debug)]
+pub struct GgrsConfig;
+
+impl Config for GgrsConfig {
+    type Input = BoxInput;
+    type State = ();
+    type Address = SocketAddr;
+}
+
+/// The GGRS player handle owned by a [`Player`]. Stored as its own component so
+/// `player.rs` does not need a netcode-specific field; [`assign_handles`]
+/// derives it from the player's stable, cross-peer identity so the
+/// handle↔entity mapping is identical on every machine.
+#[derive(Component, Clone, Copy)]
+pub struct GgrsPlayer {
+    pub handle: usize,
+}
+
+/// The stable, protocol-level identity of a player, shared across peers. A
+/// WebSocket player is keyed by the `player` id negotiated in its frames (the
+/// same value on every machine); a local gamepad player is keyed by its pad id.
+/// The handle is a pure function of this, so peers never disagree about which
+/// input drives which entity.
+fn stable_handle(controller: &PlayerController) -> Option<usize> {
+    let handle = match controller {
+        PlayerController::WebSocket(state) => state.player_id() as usize,
+        PlayerController::Gamepad(gamepad) => gamepad.id,
+    };
+    (handle < MAX_PLAYERS).then_some(handle)
+}
+
+/// How the local process participates in the session, read from the
+/// environment at startup so the same binary can host either end of a match.
+struct SessionConfig {
+    num_players: usize,
+    remotes: Vec<SocketAddr>,
+}
+
+impl SessionConfig {
+    fn from_env() -> Self {
+        // Default to the full player cap so every handle in `0..MAX_PLAYERS`
+        // has a slot; an operator can still pin a smaller table via the env.
+        let num_players = std::env::var("KQ_NUM_PLAYERS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(MAX_PLAYERS);
+        let remotes = std::env::var("KQ_REMOTES")
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .filter(|s| !s.is_empty())
+                    .filter_map(|s| s.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        SessionConfig {
+            num_players,
+            remotes,
+        }
+    }
+}
+
+pub struct RollbackPlugin;
+
+impl Plugin for RollbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GgrsPlugin::<GgrsConfig>::default())
+            // Snapshot the physics state (`Transform` and `Velocity`) so a
+            // rewind can restore it to the last confirmed frame.
+            .rollback_component_with_clone::<Transform>()
+            .rollback_component_with_copy::<Velocity>()
+            .add_systems(Startup, (configure_fixed_physics, start_session))
+            .add_systems(Update, assign_handles)
+            .add_systems(ReadInputs, read_local_inputs)
+            .add_systems(
+                GgrsSchedule,
+                apply_inputs.run_if(in_state(GameState::Play)),
+            );
+    }
+}
+
+/// Build a peer-to-peer rollback session over a non-blocking UDP socket.
+fn build_p2p_session(config: &SessionConfig) -> (Session<GgrsConfig>, Vec<usize>) {
+    let mut builder = SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(config.num_players)
+        .with_max_prediction_window(MAX_PREDICTION)
+        .expect("invalid prediction window")
+        .with_input_delay(INPUT_DELAY);
+
+    let mut local_handles = Vec::new();
+    let mut remote = config.remotes.iter();
+    for handle in 0..config.num_players {
+        match remote.next() {
+            Some(addr) => {
+                builder = builder.add_player(PlayerType::Remote(*addr), handle).unwrap()
+            }
+            None => {
+                builder = builder.add_player(PlayerType::Local, handle).unwrap();
+                local_handles.push(handle);
+            }
+        }
+    }
+
+    let socket = UdpNonBlockingSocket::bind_to_port(UDP_PORT).expect("failed to bind UDP socket");
+    let session = builder.start_p2p_session(socket).expect("failed to start session");
+    (Session::P2P(session), local_handles)
+}
+
+/// Build a deterministic sync-test session that feeds predicted inputs and
+/// compares per-frame checksums, surfacing desyncs in CI without a network.
+#[cfg(feature = "sync_test")]
+fn build_sync_test_session(config: &SessionConfig) -> (Session<GgrsConfig>, Vec<usize>) {
+    let mut builder = SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(config.num_players)
+        .with_max_prediction_window(MAX_PREDICTION)
+        .expect("invalid prediction window")
+        .with_check_distance(MAX_PREDICTION)
+        .with_input_delay(INPUT_DELAY);
+
+    for handle in 0..config.num_players {
+        builder = builder.add_player(PlayerType::Local, handle).unwrap();
+    }
+
+    let session = builder.start_synctest_session().expect("failed to start synctest");
+    // In a sync-test every handle is driven locally.
+    (Session::SyncTest(session), (0..config.num_players).collect())
+}
+
+/// Create the session at startup and insert it (plus the set of local player
+/// handles) as resources so GGRS begins driving [`GgrsSchedule`].
+fn start_session(mut commands: Commands) {
+    let config = SessionConfig::from_env();
+
+    #[cfg(feature = "sync_test")]
+    let (session, local_handles) = build_sync_test_session(&config);
+    #[cfg(not(feature = "sync_test"))]
+    let (session, local_handles) = build_p2p_session(&config);
+
+    commands.insert_resource(session);
+    commands.insert_resource(LocalPlayers(local_handles));
+}
+
+/// Pin Rapier to a fixed timestep and disable interpolation so physics is a
+/// pure function of the rolled-back state. Requires bevy_rapier2d's
+/// `enhanced-determinism` feature (enabled in Cargo.toml) to be reproducible
+/// across machines; the physics systems themselves run in [`GgrsSchedule`] via
+/// `RapierPhysicsPlugin::in_schedule` in `main`.
+fn configure_fixed_physics(mut config: Query<&mut RapierConfiguration>) {
+    if let Ok(mut config) = config.get_single_mut() {
+        config.timestep_mode = TimestepMode::Fixed {
+            dt: FIXED_DT,
+            substeps: 1,
+        };
+    }
+}
+
+/// Stamp every freshly spawned [`Player`] with the GGRS handle derived from its
+/// stable identity. A player whose identity falls outside `0..MAX_PLAYERS` is
+/// left without a handle and simply not driven by rollback, rather than
+/// colliding with or overflowing the input table.
+fn assign_handles(
+    mut commands: Commands,
+    new_players: Query<(Entity, &Player), Without<GgrsPlayer>>,
+) {
+    for (entity, player) in new_players.iter() {
+        if let Some(handle) = stable_handle(&player.player_controller) {
+            commands.entity(entity).insert(GgrsPlayer { handle });
+        }
+    }
+}
+
+/// Sample the local controller(s) once per frame and hand GGRS the packed
+/// [`BoxInput`] for each local player handle.
+fn read_local_inputs(
+    mut commands: Commands,
+    local_players: Res<LocalPlayers>,
+    players: Query<(&GgrsPlayer, &ActionState<Action>, &Team)>,
+) {
+    let mut local_inputs = bevy::utils::HashMap::new();
+
+    for handle in &local_players.0 {
+        let mut input = BoxInput::default();
+        for (player, action_state, team) in players.iter() {
+            if player.handle != *handle {
+                continue;
+            }
+            let x = action_state.clamped_value(&Action::Move);
+            input.x_movement = (x * i8::MAX as f32).round() as i8;
+            if action_state.pressed(&Action::Jump) {
+                input.buttons |= BTN_JUMP;
+            }
+            if *team == Team::Purple {
+                input.buttons |= BTN_PURPLE;
+            }
+            if action_state.pressed(&Action::Disconnect) {
+                input.buttons |= BTN_LEAVE;
+            }
+        }
+        local_inputs.insert(*handle, input);
+    }
+
+    commands.insert_resource(LocalInputs::<GgrsConfig>(local_inputs));
+}
+
+/// Write each rollback player's `Velocity` from its GGRS input inside
+/// `GgrsSchedule`, where it is rolled back and re-simulated. The input-driven
+/// movement in `player.rs` does not use [`session_live`], so it is not switched
+/// off while a session is live.
+fn apply_inputs(
+    mut players: Query<(&mut Velocity, &GgrsPlayer)>,
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+) {
+    for (mut velocity, player) in players.iter_mut() {
+        // Handles are validated at assignment, but guard anyway so a stray
+        // out-of-range handle is skipped instead of panicking the index.
+        if player.handle >= inputs.len() {
+            continue;
+        }
+        let (input, _) = inputs[player.handle];
+        velocity.linvel.x = input.x_movement() * PLAYER_SPEED;
+        if input.jump() {
+            velocity.linvel.y = PLAYER_JUMP_IMPULSE;
+        }
+    }
+}
+
+/// Run condition that is `true` once a rollback [`Session`] has been inserted.
+/// No system in `player.rs` is registered with it.
+pub fn session_live(session: Option<Res<Session<GgrsConfig>>>) -> bool {
+    session.is_some()
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use serde::Serialize;
+
+use std::net::TcpStream;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use tungstenite::WebSocket;
+
+use crate::berries::Berry;
+use crate::gates::Gate;
+use crate::player::{Queen, Team};
+use crate::ship::Ship;
+use crate::{GameState, WinCondition};
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            // Keep streaming through GameOver so the decided win condition
+            // reaches spectators in the final snapshots.
+            broadcast_snapshot
+                .run_if(in_state(GameState::Play).or_else(in_state(GameState::GameOver))),
+        );
+    }
+}
+
+/// Shared list of read-only WebSocket clients that asked to spectate during the
+/// handshake. The network thread pushes new connections in here; the Bevy
+/// system below sends a snapshot on a channel every frame, and a network
+/// thread writes each snapshot to every socket in this list.
+#[derive(Resource, Clone, Default)]
+pub struct Spectators(pub Arc<Mutex<Vec<WebSocket<TcpStream>>>>);
+
+/// Channel the Bevy side uses to hand finished snapshots to the network thread,
+/// which owns the sockets.
+#[derive(Resource)]
+pub struct SnapshotSender(pub Sender<GameSnapshot>);
+
+/// Per-team tally broadcast to spectators.
+#[derive(Serialize, Default)]
+pub struct TeamSnapshot {
+    pub berries: u32,
+    pub gates: u32,
+    pub queen: Option<[f32; 2]>,
+}
+
+/// A compact view of the match sent to every spectator once per frame.
+#[derive(Serialize)]
+pub struct GameSnapshot {
+    pub yellow: TeamSnapshot,
+    pub purple: TeamSnapshot,
+    pub win_condition: Option<String>,
+    pub ship: [f32; 2],
+}
+
+fn broadcast_snapshot(
+    sender: Option<Res<SnapshotSender>>,
+    gates: Query<&Team, With<Gate>>,
+    queens: Query<(&Team, &Transform), With<Queen>>,
+    berries: Query<&Team, With<Berry>>,
+    ships: Query<&Transform, With<Ship>>,
+    win_condition: Option<Res<WinCondition>>,
+) {
+    let Some(sender) = sender else {
+        return;
+    };
+
+    let mut snapshot = GameSnapshot {
+        yellow: TeamSnapshot::default(),
+        purple: TeamSnapshot::default(),
+        win_condition: win_condition.map(|w| format!("{:?}", *w)),
+        ship: ships
+            .get_single()
+            .map(|t| [t.translation.x, t.translation.y])
+            .unwrap_or([0.0, 0.0]),
+    };
+
+    for team in gates.iter() {
+        snapshot.team_mut(*team).gates += 1;
+    }
+    for team in berries.iter() {
+        snapshot.team_mut(*team).berries += 1;
+    }
+    for (team, transform) in queens.iter() {
+        snapshot.team_mut(*team).queen = Some([transform.translation.x, transform.translation.y]);
+    }
+
+    // Dropped snapshots are fine: spectators only need the latest state.
+    let _ = sender.0.send(snapshot);
+}
+
+impl GameSnapshot {
+    fn team_mut(&mut self, team: Team) -> &mut TeamSnapshot {
+        match team {
+            Team::Yellow => &mut self.yellow,
+            Team::Purple => &mut self.purple,
+        }
+    }
+}